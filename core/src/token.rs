@@ -1,15 +1,38 @@
 // Ported from librespot-java. Relicensed under MIT with permission.
 
 use crate::mercury::MercuryError;
+use crate::protocol::clienttoken_http::{
+    ClientTokenRequest, ClientTokenRequestType, ClientTokenResponse,
+};
+use crate::protocol::login5::{ChallengeSolution, ChallengeSolutions, LoginRequest, LoginResponse};
 
+use protobuf::Message;
 use serde::Deserialize;
+use sha1::{Digest, Sha1};
 
+use std::collections::HashMap;
 use std::error::Error;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use tokio::sync::Mutex as AsyncMutex;
+
 component! {
     TokenProvider : TokenProviderInner {
         tokens: Vec<Token> = vec![],
+        login5_tokens: Vec<Token> = vec![],
+        client_tokens: Vec<ClientToken> = vec![],
+        fetch_locks: HashMap<(Option<String>, Vec<String>), Arc<AsyncMutex<()>>> = HashMap::new(),
+    }
+}
+
+pub struct AutoRefreshHandle {
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl AutoRefreshHandle {
+    pub fn shutdown(self) {
+        self.task.abort();
     }
 }
 
@@ -19,6 +42,9 @@ pub struct Token {
     access_token: String,
     scopes: Vec<String>,
     timestamp: Instant,
+    // `None` for a Keymaster token, `Some(client_id)` for a login5 one, so
+    // the two issuers' caches can't be matched against each other by scope.
+    client_id: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -29,8 +55,20 @@ struct TokenData {
     scope: Vec<String>,
 }
 
+#[derive(Clone, Debug)]
+pub struct ClientToken {
+    client_id: String,
+    access_token: String,
+    refresh_after: Duration,
+    timestamp: Instant,
+}
+
 impl TokenProvider {
     const KEYMASTER_CLIENT_ID: &'static str = "65b708073fc0480ea92a077233ca87bd";
+    const CLIENT_TOKEN_URL: &'static str = "https://clienttoken.spotify.com/v1/clienttoken";
+    const LOGIN5_URL: &'static str = "https://login5.spotify.com/v3/login";
+    const LOGIN5_MAX_CHALLENGE_ATTEMPTS: u8 = 3;
+    const MAX_RETRY_ATTEMPTS: u32 = 5;
 
     fn find_token(&self, scopes: Vec<String>) -> Option<usize> {
         self.lock(|inner| {
@@ -43,20 +81,148 @@ impl TokenProvider {
         })
     }
 
-    pub async fn get_token(&self, scopes: Vec<String>) -> Result<Token, MercuryError> {
-        if scopes.is_empty() {
-            return Err(MercuryError);
-        }
+    fn find_login5_token(&self, client_id: &str, scopes: Vec<String>) -> Option<usize> {
+        self.lock(|inner| {
+            for i in 0..inner.login5_tokens.len() {
+                if inner.login5_tokens[i].client_id.as_deref() == Some(client_id)
+                    && inner.login5_tokens[i].in_scopes(scopes.clone())
+                {
+                    return Some(i);
+                }
+            }
+            None
+        })
+    }
+
+    fn find_client_token(&self, client_id: &str) -> Option<usize> {
+        self.lock(|inner| {
+            inner
+                .client_tokens
+                .iter()
+                .position(|token| token.client_id == client_id)
+        })
+    }
 
-        if let Some(index) = self.find_token(scopes.clone()) {
-            let cached_token = self.lock(|inner| inner.tokens[index].clone());
+    pub async fn get_client_token(&self, client_id: String) -> Result<ClientToken, crate::Error> {
+        if let Some(index) = self.find_client_token(&client_id) {
+            let cached_token = self.lock(|inner| inner.client_tokens[index].clone());
             if cached_token.is_expired() {
-                self.lock(|inner| inner.tokens.remove(index));
+                self.lock(|inner| inner.client_tokens.remove(index));
             } else {
                 return Ok(cached_token);
             }
         }
 
+        trace!(
+            "Requested client token for client_id {} unavailable or expired, requesting new one.",
+            client_id
+        );
+
+        let mut request = ClientTokenRequest::new();
+        request.set_request_type(ClientTokenRequestType::REQUEST_CLIENT_DATA_REQUEST);
+        let client_data = request.mut_client_data();
+        client_data.set_client_id(client_id.clone());
+        client_data.set_client_version(crate::version::SEMVER.to_owned());
+        client_data
+            .mut_connectivity_sdk_data()
+            .set_device_id(self.session().device_id().to_owned());
+
+        let body = request.write_to_bytes()?;
+        let response = self.post_with_retry(Self::CLIENT_TOKEN_URL, body).await?;
+
+        let data = ClientTokenResponse::parse_from_bytes(&response)?;
+        let granted = data.granted_token();
+
+        let token = ClientToken::new(
+            client_id,
+            granted.token().to_owned(),
+            granted.refresh_after_seconds() as u64,
+        );
+        trace!("Got client token: {:?}", token);
+        self.lock(|inner| inner.client_tokens.push(token.clone()));
+
+        Ok(token)
+    }
+
+    pub async fn get_token(&self, scopes: Vec<String>) -> Result<Token, MercuryError> {
+        if scopes.is_empty() {
+            return Err(MercuryError);
+        }
+
+        if let Some(token) = self.cached_token(&scopes) {
+            return Ok(token);
+        }
+
+        // Serialize concurrent requests for the same scope set onto a
+        // single fetch: everyone but the first caller blocks here, then
+        // finds the token the first caller just cached below.
+        let fetch_lock = self.lock(|inner| {
+            inner
+                .fetch_locks
+                .entry((None, scopes.clone()))
+                .or_insert_with(|| Arc::new(AsyncMutex::new(())))
+                .clone()
+        });
+        let _guard = fetch_lock.lock().await;
+
+        if let Some(token) = self.cached_token(&scopes) {
+            return Ok(token);
+        }
+
+        let token = self.fetch_token(scopes).await?;
+        self.lock(|inner| inner.tokens.push(token.clone()));
+        Ok(token)
+    }
+
+    fn cached_token(&self, scopes: &[String]) -> Option<Token> {
+        let index = self.find_token(scopes.to_vec())?;
+        let cached_token = self.lock(|inner| inner.tokens[index].clone());
+        if cached_token.is_expired() {
+            self.lock(|inner| inner.tokens.remove(index));
+            None
+        } else {
+            Some(cached_token)
+        }
+    }
+
+    fn cached_login5_token(&self, client_id: &str, scopes: &[String]) -> Option<Token> {
+        let index = self.find_login5_token(client_id, scopes.to_vec())?;
+        let cached_token = self.lock(|inner| inner.login5_tokens[index].clone());
+        if cached_token.is_expired() {
+            self.lock(|inner| inner.login5_tokens.remove(index));
+            None
+        } else {
+            Some(cached_token)
+        }
+    }
+
+    async fn refresh_token(&self, scopes: Vec<String>) -> Result<Token, MercuryError> {
+        let token = self.fetch_token(scopes.clone()).await?;
+        self.lock(|inner| upsert_keymaster_token(&mut inner.tokens, token.clone()));
+        trace!("Proactively refreshed Keymaster token in scopes {:?}", scopes);
+        Ok(token)
+    }
+
+    // Like refresh_token, but keeps a login5 token on the login5 path
+    // instead of silently replacing it with a Keymaster one.
+    async fn refresh_login5_token(
+        &self,
+        client_id: String,
+        scopes: Vec<String>,
+    ) -> Result<Token, crate::Error> {
+        let token = self
+            .fetch_token_login5(client_id.clone(), scopes.clone())
+            .await?;
+        self.lock(|inner| upsert_login5_token(&mut inner.login5_tokens, token.clone()));
+        trace!(
+            "Proactively refreshed login5 token for client_id {} in scopes {:?}",
+            client_id,
+            scopes
+        );
+        Ok(token)
+    }
+
+    async fn fetch_token(&self, scopes: Vec<String>) -> Result<Token, MercuryError> {
         trace!(
             "Requested token in scopes {:?} unavailable or expired, requesting new token.",
             scopes
@@ -68,23 +234,320 @@ impl TokenProvider {
             Self::KEYMASTER_CLIENT_ID,
             self.session().device_id()
         );
-        let request = self.session().mercury().get(query_uri);
-        let response = request.await?;
-
-        if response.status_code == 200 {
-            let data = response
-                .payload
-                .first()
-                .expect("No tokens received")
-                .to_vec();
-            let token = Token::new(String::from_utf8(data).unwrap()).map_err(|_| MercuryError)?;
-            trace!("Got token: {:?}", token);
-            self.lock(|inner| inner.tokens.push(token.clone()));
-            Ok(token)
+
+        let response = mercury_get_with_retry(&self.session(), query_uri).await?;
+        if response.status_code != 200 {
+            return Err(MercuryError);
+        }
+        let data = response
+            .payload
+            .first()
+            .expect("No tokens received")
+            .to_vec();
+        let token = Token::new(String::from_utf8(data).unwrap()).map_err(|_| MercuryError)?;
+        trace!("Got token: {:?}", token);
+        Ok(token)
+    }
+
+    pub fn start_auto_refresh(&self, refresh_fraction: f32) -> AutoRefreshHandle {
+        let provider = self.clone();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+
+                let (due_for_refresh, due_for_login5_refresh): (
+                    Vec<Vec<String>>,
+                    Vec<(String, Vec<String>)>,
+                ) = provider.lock(|inner| {
+                    let keymaster = inner
+                        .tokens
+                        .iter()
+                        .filter(|token| {
+                            !token.is_expired()
+                                && token.lifetime_fraction_elapsed() >= refresh_fraction
+                        })
+                        .map(|token| token.scopes.clone())
+                        .collect();
+                    let login5 = inner
+                        .login5_tokens
+                        .iter()
+                        .filter(|token| {
+                            !token.is_expired()
+                                && token.lifetime_fraction_elapsed() >= refresh_fraction
+                        })
+                        .filter_map(|token| {
+                            token
+                                .client_id
+                                .clone()
+                                .map(|client_id| (client_id, token.scopes.clone()))
+                        })
+                        .collect();
+                    (keymaster, login5)
+                });
+
+                for scopes in due_for_refresh {
+                    if let Err(e) = provider.refresh_token(scopes.clone()).await {
+                        trace!(
+                            "Background refresh of Keymaster token in scopes {:?} failed: {:?}",
+                            scopes,
+                            e
+                        );
+                    }
+                }
+
+                for (client_id, scopes) in due_for_login5_refresh {
+                    if let Err(e) = provider
+                        .refresh_login5_token(client_id.clone(), scopes.clone())
+                        .await
+                    {
+                        trace!(
+                            "Background refresh of login5 token for client_id {} in scopes {:?} failed: {:?}",
+                            client_id,
+                            scopes,
+                            e
+                        );
+                    }
+                }
+            }
+        });
+
+        AutoRefreshHandle { task }
+    }
+
+    pub async fn get_token_login5(
+        &self,
+        client_id: String,
+        scopes: Vec<String>,
+    ) -> Result<Token, crate::Error> {
+        if scopes.is_empty() {
+            return Err(MercuryError.into());
+        }
+
+        if let Some(token) = self.cached_login5_token(&client_id, &scopes) {
+            return Ok(token);
+        }
+
+        // Serialize concurrent requests for the same client_id/scope set
+        // onto a single fetch, same as `get_token` does for Keymaster.
+        let fetch_lock = self.lock(|inner| {
+            inner
+                .fetch_locks
+                .entry((Some(client_id.clone()), scopes.clone()))
+                .or_insert_with(|| Arc::new(AsyncMutex::new(())))
+                .clone()
+        });
+        let _guard = fetch_lock.lock().await;
+
+        if let Some(token) = self.cached_login5_token(&client_id, &scopes) {
+            return Ok(token);
+        }
+
+        let token = self.fetch_token_login5(client_id, scopes).await?;
+        self.lock(|inner| inner.login5_tokens.push(token.clone()));
+        Ok(token)
+    }
+
+    async fn fetch_token_login5(
+        &self,
+        client_id: String,
+        scopes: Vec<String>,
+    ) -> Result<Token, crate::Error> {
+        let mut request = LoginRequest::new();
+        request.set_client_id(client_id.clone());
+        let credentials = self.session().credentials();
+        request.mut_stored_credential().set_username(credentials.username.clone());
+        request
+            .mut_stored_credential()
+            .set_data(credentials.auth_data.clone());
+
+        let mut response = self.login5_request(request.clone()).await?;
+
+        let mut attempts = 0;
+        while response.has_challenges() {
+            attempts += 1;
+            if attempts > Self::LOGIN5_MAX_CHALLENGE_ATTEMPTS {
+                trace!("Too many login5 challenge attempts, giving up.");
+                return Err(MercuryError.into());
+            }
+
+            let mut solutions = ChallengeSolutions::new();
+            for challenge in &response.challenges().challenges {
+                let hashcash = challenge.hashcash();
+                let prefix = hashcash.prefix().to_vec();
+                let difficulty = hashcash.length();
+                // Solving runs a tight, non-yielding SHA1 loop that can take
+                // a while for a non-trivial difficulty; run it on a blocking
+                // thread so it doesn't stall the async runtime.
+                let suffix = tokio::task::spawn_blocking(move || solve_hashcash(&prefix, difficulty))
+                    .await
+                    .expect("hashcash solver task panicked");
+                let mut solution = ChallengeSolution::new();
+                solution.mut_hashcash().set_suffix(suffix.to_vec());
+                solutions.solutions.push(solution);
+            }
+
+            let mut retry_request = request.clone();
+            retry_request.set_login_context(response.login_context().to_owned());
+            retry_request.set_challenge_solutions(solutions);
+
+            response = self.login5_request(retry_request).await?;
+        }
+
+        if response.has_error() {
+            trace!("Login5 returned an error: {:?}", response.error());
+            return Err(MercuryError.into());
+        }
+
+        let ok = response.ok();
+        let token = Token::from_parts(
+            ok.access_token().to_owned(),
+            ok.access_token_expires_in() as u64,
+            scopes,
+            Some(client_id),
+        );
+        trace!("Got login5 token: {:?}", token);
+
+        Ok(token)
+    }
+
+    async fn login5_request(&self, request: LoginRequest) -> Result<LoginResponse, crate::Error> {
+        let body = request.write_to_bytes()?;
+        let response = self.post_with_retry(Self::LOGIN5_URL, body).await?;
+
+        Ok(LoginResponse::parse_from_bytes(&response)?)
+    }
+
+    async fn post_with_retry(&self, url: &str, body: Vec<u8>) -> Result<Vec<u8>, crate::Error> {
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .session()
+                .http_client()
+                .request(&reqwest::Method::POST, url, None, Some(body.clone()))
+                .await?;
+
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                && attempt < Self::MAX_RETRY_ATTEMPTS
+            {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .map(Duration::from_secs);
+
+                attempt += 1;
+                let delay = Self::backoff_delay(attempt, retry_after);
+                trace!(
+                    "Request to {} rate-limited (attempt {}/{}), retrying in {:?}",
+                    url,
+                    attempt,
+                    Self::MAX_RETRY_ATTEMPTS,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            // Surfaces a clear HTTP error for any remaining non-2xx status
+            // (including a 429 once retries are exhausted) instead of
+            // handing an error body to the protobuf parser as if it were a
+            // valid response.
+            let response = response.error_for_status()?;
+            return Ok(response.bytes().await?.to_vec());
+        }
+    }
+
+    fn backoff_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after
+            .unwrap_or_else(|| DEFAULT_RETRY_AFTER.saturating_mul(1 << attempt.min(4)))
+            .min(MAX_BACKOFF)
+    }
+}
+
+// Used by fetch_token; its 429 handling shares backoff_delay with
+// post_with_retry. Pulled out as a free function so other Mercury-backed
+// fetches (e.g. Track::get/Episode::get) can reuse it.
+pub async fn mercury_get_with_retry(
+    session: &crate::Session,
+    uri: impl Into<String>,
+) -> Result<crate::mercury::MercuryResponse, MercuryError> {
+    let uri = uri.into();
+    let mut attempt = 0;
+    loop {
+        let response = session.mercury().get(uri.clone()).await?;
+
+        match response.status_code {
+            429 if attempt < TokenProvider::MAX_RETRY_ATTEMPTS => {
+                attempt += 1;
+                let delay = TokenProvider::backoff_delay(attempt, None);
+                trace!(
+                    "Mercury GET {} rate-limited (attempt {}/{}), retrying in {:?}",
+                    uri,
+                    attempt,
+                    TokenProvider::MAX_RETRY_ATTEMPTS,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+            _ => return Ok(response),
+        }
+    }
+}
+
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// Pulled out of refresh_token so the swap-in behavior can be tested without
+// a real TokenProvider/Session.
+fn upsert_keymaster_token(tokens: &mut Vec<Token>, token: Token) {
+    match tokens.iter().position(|t| t.scopes == token.scopes) {
+        Some(index) => tokens[index] = token,
+        None => tokens.push(token),
+    }
+}
+
+// Same as upsert_keymaster_token, but also keyed on client_id so a login5
+// token is only ever replaced by one minted for the same client.
+fn upsert_login5_token(tokens: &mut Vec<Token>, token: Token) {
+    match tokens
+        .iter()
+        .position(|t| t.client_id == token.client_id && t.scopes == token.scopes)
+    {
+        Some(index) => tokens[index] = token,
+        None => tokens.push(token),
+    }
+}
+
+fn solve_hashcash(prefix: &[u8], difficulty: i32) -> [u8; 16] {
+    let mut counter: u128 = 0;
+    loop {
+        let suffix = counter.to_be_bytes();
+
+        let mut hasher = Sha1::new();
+        hasher.update(prefix);
+        hasher.update(suffix);
+        let digest = hasher.finalize();
+
+        if trailing_zero_bits(&digest) >= difficulty as u32 {
+            return suffix;
+        }
+        counter += 1;
+    }
+}
+
+fn trailing_zero_bits(bytes: &[u8]) -> u32 {
+    let mut zero_bits = 0;
+    for &byte in bytes.iter().rev() {
+        if byte == 0 {
+            zero_bits += 8;
         } else {
-            Err(MercuryError)
+            zero_bits += byte.trailing_zeros();
+            break;
         }
     }
+    zero_bits
 }
 
 impl Token {
@@ -97,13 +560,33 @@ impl Token {
             access_token: data.access_token,
             scopes: data.scope,
             timestamp: Instant::now(),
+            client_id: None,
         })
     }
 
+    fn from_parts(
+        access_token: String,
+        expires_in: u64,
+        scopes: Vec<String>,
+        client_id: Option<String>,
+    ) -> Self {
+        Self {
+            expires_in: Duration::from_secs(expires_in),
+            access_token,
+            scopes,
+            timestamp: Instant::now(),
+            client_id,
+        }
+    }
+
     pub fn is_expired(&self) -> bool {
         self.timestamp + (self.expires_in - Self::EXPIRY_THRESHOLD) < Instant::now()
     }
 
+    fn lifetime_fraction_elapsed(&self) -> f32 {
+        self.timestamp.elapsed().as_secs_f32() / self.expires_in.as_secs_f32()
+    }
+
     pub fn in_scope(&self, scope: String) -> bool {
         for s in &self.scopes {
             if *s == scope {
@@ -122,3 +605,171 @@ impl Token {
         true
     }
 }
+
+impl ClientToken {
+    fn new(client_id: String, access_token: String, refresh_after_seconds: u64) -> Self {
+        Self {
+            client_id,
+            access_token,
+            refresh_after: Duration::from_secs(refresh_after_seconds),
+            timestamp: Instant::now(),
+        }
+    }
+
+    pub fn access_token(&self) -> &str {
+        &self.access_token
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.timestamp + self.refresh_after.saturating_sub(Token::EXPIRY_THRESHOLD) < Instant::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_zero_bits_counts_from_the_end() {
+        assert_eq!(trailing_zero_bits(&[0b0000_0001]), 0);
+        assert_eq!(trailing_zero_bits(&[0b0000_0010]), 1);
+        assert_eq!(trailing_zero_bits(&[0b0000_0000]), 8);
+        assert_eq!(trailing_zero_bits(&[0b0000_0001, 0b0000_0000]), 8);
+        assert_eq!(trailing_zero_bits(&[0b0000_0000, 0b0000_0000]), 16);
+    }
+
+    #[test]
+    fn solve_hashcash_meets_the_requested_difficulty() {
+        let prefix = b"test-prefix";
+        let difficulty = 12;
+        let suffix = solve_hashcash(prefix, difficulty);
+
+        let mut hasher = Sha1::new();
+        hasher.update(prefix);
+        hasher.update(suffix);
+        let digest = hasher.finalize();
+
+        assert!(trailing_zero_bits(&digest) >= difficulty as u32);
+    }
+
+    #[test]
+    fn backoff_delay_prefers_retry_after() {
+        let delay = TokenProvider::backoff_delay(1, Some(Duration::from_secs(30)));
+        assert_eq!(delay, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn backoff_delay_doubles_per_attempt_without_retry_after() {
+        assert_eq!(TokenProvider::backoff_delay(1, None), DEFAULT_RETRY_AFTER * 2);
+        assert_eq!(TokenProvider::backoff_delay(2, None), DEFAULT_RETRY_AFTER * 4);
+        assert_eq!(TokenProvider::backoff_delay(3, None), DEFAULT_RETRY_AFTER * 8);
+    }
+
+    #[test]
+    fn backoff_delay_is_capped() {
+        assert_eq!(TokenProvider::backoff_delay(10, None), MAX_BACKOFF);
+        assert_eq!(
+            TokenProvider::backoff_delay(1, Some(Duration::from_secs(3600))),
+            MAX_BACKOFF
+        );
+    }
+
+    #[test]
+    fn lifetime_fraction_elapsed_starts_near_zero() {
+        let token = Token::from_parts(
+            "access-token".to_owned(),
+            3600,
+            vec!["scope".to_owned()],
+            None,
+        );
+        assert!(token.lifetime_fraction_elapsed() < 0.01);
+    }
+
+    #[test]
+    fn upsert_keymaster_token_replaces_matching_scopes_in_place() {
+        let mut tokens = vec![Token::from_parts(
+            "old".to_owned(),
+            3600,
+            vec!["a".to_owned()],
+            None,
+        )];
+        let new = Token::from_parts("new".to_owned(), 3600, vec!["a".to_owned()], None);
+
+        upsert_keymaster_token(&mut tokens, new);
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].access_token, "new");
+    }
+
+    #[test]
+    fn upsert_login5_token_does_not_replace_a_token_from_a_different_client() {
+        let mut tokens = vec![Token::from_parts(
+            "client-a".to_owned(),
+            3600,
+            vec!["a".to_owned()],
+            Some("client-a".to_owned()),
+        )];
+        let other_client = Token::from_parts(
+            "client-b".to_owned(),
+            3600,
+            vec!["a".to_owned()],
+            Some("client-b".to_owned()),
+        );
+
+        upsert_login5_token(&mut tokens, other_client);
+
+        assert_eq!(tokens.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn coalescing_lock_pattern_collapses_concurrent_fetches_into_one() {
+        // Mirrors the double-checked-lock pattern get_token/get_token_login5
+        // use: a per-key Arc<AsyncMutex<()>> serializes concurrent callers
+        // for the same key onto a single fetch, with a cache check both
+        // before and after acquiring the lock. TokenProvider itself needs a
+        // real Session to drive end-to-end, so this exercises the pattern
+        // directly instead.
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex as StdMutex;
+
+        let cache: Arc<StdMutex<Option<u32>>> = Arc::new(StdMutex::new(None));
+        let fetch_locks: Arc<StdMutex<HashMap<&'static str, Arc<AsyncMutex<()>>>>> =
+            Arc::new(StdMutex::new(HashMap::new()));
+        let fetch_count = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for _ in 0..8 {
+            let cache = cache.clone();
+            let fetch_locks = fetch_locks.clone();
+            let fetch_count = fetch_count.clone();
+            handles.push(tokio::spawn(async move {
+                if let Some(value) = *cache.lock().unwrap() {
+                    return value;
+                }
+
+                let fetch_lock = fetch_locks
+                    .lock()
+                    .unwrap()
+                    .entry("scope")
+                    .or_insert_with(|| Arc::new(AsyncMutex::new(())))
+                    .clone();
+                let _guard = fetch_lock.lock().await;
+
+                if let Some(value) = *cache.lock().unwrap() {
+                    return value;
+                }
+
+                fetch_count.fetch_add(1, Ordering::SeqCst);
+                let value = 42;
+                *cache.lock().unwrap() = Some(value);
+                value
+            }));
+        }
+
+        for handle in handles {
+            assert_eq!(handle.await.unwrap(), 42);
+        }
+
+        assert_eq!(fetch_count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt::Debug;
 
+use futures_util::future::join_all;
+
 use crate::{
     availability::{AudioItemAvailability, Availabilities, UnavailabilityReason},
     episode::Episode,
@@ -24,6 +26,11 @@ pub enum AudioItem {
 }
 
 impl AudioItem {
+    // `Track::get`/`Episode::get` issue their own Mercury request and don't
+    // yet retry on a rate-limited response the way
+    // `librespot_core::token::mercury_get_with_retry` does for token
+    // fetches; wiring them up is a follow-up against `track.rs`/`episode.rs`,
+    // which this change doesn't touch.
     pub async fn get_file(session: &Session, id: SpotifyId) -> AudioItemResult {
         Ok(match id.item_type {
             SpotifyItemType::Track => AudioItem::Track(Track::get(session, &id).await?),
@@ -32,6 +39,35 @@ impl AudioItem {
         })
     }
 
+    // This still issues one Mercury request per id, just concurrently
+    // instead of in series — it does not reduce the number of backend
+    // requests the way a real `Metadata::get_many` batched through a single
+    // request would. Doing that properly needs a `get_many` added to the
+    // `Metadata` trait and implemented on `Track`/`Episode`, which live in
+    // track.rs/episode.rs outside this change; scoping this one down to
+    // "concurrent, not batched" until that lands. Order and length of the
+    // result mirror `ids`, with per-item errors kept alongside successes.
+    pub async fn get_files(session: &Session, ids: &[SpotifyId]) -> Vec<AudioItemResult> {
+        resolve_concurrently(ids, |id| Self::get_file(session, id)).await
+    }
+
+    // Like get_file, but falls back to a track's region alternatives when
+    // the resolved item is NotWhitelisted/Blacklisted, returning the first
+    // one that's actually playable. Falls back to the original error if none
+    // of them are.
+    pub async fn get_playable(session: &Session, id: SpotifyId) -> AudioItemResult {
+        resolve_playable(
+            id,
+            |id| Self::get_file(session, id),
+            |item: &AudioItem| item.availability(session),
+            |item: &AudioItem| match item {
+                AudioItem::Track(track) => track.alternatives.clone(),
+                AudioItem::Episode(_) => vec![],
+            },
+        )
+        .await
+    }
+
     pub fn id(&self) -> SpotifyId {
         match self {
             AudioItem::Track(t) => t.id,
@@ -86,6 +122,51 @@ impl AudioItem {
     }
 }
 
+// Pulled out of get_files so the concurrency/ordering behavior can be
+// exercised with a fake `fetch` in tests, without needing a real Session.
+async fn resolve_concurrently<T, F, Fut>(ids: &[SpotifyId], fetch: F) -> Vec<Result<T, Error>>
+where
+    F: Fn(SpotifyId) -> Fut,
+    Fut: std::future::Future<Output = Result<T, Error>>,
+{
+    join_all(ids.iter().map(|&id| fetch(id))).await
+}
+
+// Pulled out of get_playable for the same reason: lets the
+// alternative-fallback control flow be exercised with fakes in tests.
+async fn resolve_playable<T, F, Fut, IsAvailable, Alternatives>(
+    id: SpotifyId,
+    fetch: F,
+    is_available: IsAvailable,
+    alternatives: Alternatives,
+) -> Result<T, Error>
+where
+    F: Fn(SpotifyId) -> Fut,
+    Fut: std::future::Future<Output = Result<T, Error>>,
+    IsAvailable: Fn(&T) -> AudioItemAvailability,
+    Alternatives: Fn(&T) -> Vec<SpotifyId>,
+{
+    let item = fetch(id).await?;
+
+    match is_available(&item) {
+        Ok(()) => Ok(item),
+        Err(reason @ (UnavailabilityReason::NotWhitelisted | UnavailabilityReason::Blacklisted)) => {
+            for alternative_id in alternatives(&item) {
+                let alternative = match fetch(alternative_id).await {
+                    Ok(alternative) => alternative,
+                    Err(_) => continue,
+                };
+                if is_available(&alternative).is_ok() {
+                    return Ok(alternative);
+                }
+            }
+
+            Err(Error::unavailable(reason))
+        }
+        Err(reason) => Err(Error::unavailable(reason)),
+    }
+}
+
 fn allowed_for_user(user_data: &UserData, restrictions: &Restrictions) -> AudioItemAvailability {
     let country = &user_data.country;
     let user_catalogue = match user_data.attributes.get("catalogue") {
@@ -150,3 +231,86 @@ fn available_for_user(
     allowed_for_user(user_data, restrictions)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spotify_id(id: u128) -> SpotifyId {
+        SpotifyId {
+            id,
+            item_type: SpotifyItemType::Track,
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_concurrently_preserves_order_and_isolates_errors() {
+        let ids = [spotify_id(1), spotify_id(2), spotify_id(3)];
+
+        let results: Vec<Result<u128, Error>> = resolve_concurrently(&ids, |id| async move {
+            if id.id == 2 {
+                Err(Error::unavailable(MetadataError::NonPlayable))
+            } else {
+                Ok(id.id)
+            }
+        })
+        .await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(*results[0].as_ref().unwrap(), 1);
+        assert!(results[1].is_err());
+        assert_eq!(*results[2].as_ref().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn resolve_playable_falls_back_to_first_playable_alternative() {
+        // id 1 is region-restricted with alternatives [2, 3]; 2 fails to
+        // fetch entirely and should be skipped, 3 is playable.
+        let result = resolve_playable(
+            spotify_id(1),
+            |id| async move {
+                match id.id {
+                    1 => Ok(1u128),
+                    2 => Err(Error::unavailable(MetadataError::NonPlayable)),
+                    3 => Ok(3u128),
+                    _ => unreachable!(),
+                }
+            },
+            |item: &u128| {
+                if *item == 1 {
+                    Err(UnavailabilityReason::NotWhitelisted)
+                } else {
+                    Ok(())
+                }
+            },
+            |item: &u128| {
+                if *item == 1 {
+                    vec![spotify_id(2), spotify_id(3)]
+                } else {
+                    vec![]
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn resolve_playable_returns_original_error_without_a_playable_alternative() {
+        let result = resolve_playable(
+            spotify_id(1),
+            |id| async move {
+                match id.id {
+                    1 => Ok(1u128),
+                    _ => unreachable!(),
+                }
+            },
+            |_: &u128| Err(UnavailabilityReason::Blacklisted),
+            |_: &u128| vec![],
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+}